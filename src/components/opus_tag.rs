@@ -1,10 +1,192 @@
 use crate::*;
 use opus_headers;
 
-use opus_headers::{CommentHeader, IdentificationHeader, OpusHeaders as OpusInnerTag};
-use std::borrow::BorrowMut;
-use std::collections::hash_map::RandomState;
-use std::collections::HashMap;
+use opus_headers::IdentificationHeader;
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::io::{Read, Seek, Write};
+use std::time::Duration;
+
+/// The Opus reference clock rate, used to turn granule positions into sample
+/// counts regardless of the stream's actual `input_sample_rate`.
+const OPUS_CLOCK_RATE: u64 = 48_000;
+
+/// An ordered store of Vorbis comment key/value pairs.
+///
+/// Unlike a `HashMap`, this preserves the original field order and allows a
+/// key to appear more than once, which the Vorbis comment spec explicitly
+/// permits (e.g. multiple `ARTIST=` entries for a multi-artist track).
+#[derive(Default)]
+pub struct VorbisComments {
+	pub vendor: String,
+	items: Vec<(String, String)>,
+}
+
+impl VorbisComments {
+	/// Returns the first value stored under `key`
+	pub fn get_first(&self, key: &str) -> Option<&str> {
+		self.items
+			.iter()
+			.find(|(k, _)| k.eq_ignore_ascii_case(key))
+			.map(|(_, v)| v.as_str())
+	}
+
+	/// Returns every value stored under `key`, in insertion order
+	pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		self.items
+			.iter()
+			.filter(move |(k, _)| k.eq_ignore_ascii_case(key))
+			.map(|(_, v)| v.as_str())
+	}
+
+	/// Inserts a `key`/`value` pair
+	///
+	/// If `replace_all` is `true`, any existing values stored under `key` are
+	/// removed first. Otherwise, `value` is appended alongside them.
+	pub fn insert(&mut self, key: &str, value: String, replace_all: bool) {
+		if replace_all {
+			self.items.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+		}
+
+		self.items.push((key.to_string(), value));
+	}
+
+	/// Overwrites the first value stored under `key`, or appends it if the key
+	/// isn't present
+	pub fn set_first(&mut self, key: &str, value: &str) {
+		match self.items.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case(key)) {
+			Some(entry) => entry.1 = value.to_string(),
+			None => self.items.push((key.to_string(), value.to_string())),
+		}
+	}
+
+	/// Removes every value stored under `key`
+	pub fn remove(&mut self, key: &str) {
+		self.items.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+	}
+}
+
+/// The data backing an [`OpusTag`]
+///
+/// This stands in for `opus_headers::OpusHeaders`, replacing its
+/// `HashMap`-backed comment storage (which silently collapses repeated keys)
+/// with [`VorbisComments`].
+pub struct OpusInnerTag {
+	pub id: IdentificationHeader,
+	pub comments: VorbisComments,
+}
+
+// Vorbis comment key picture data is stored under (the Xiph METADATA_BLOCK_PICTURE spec).
+const METADATA_BLOCK_PICTURE_KEY: &str = "METADATA_BLOCK_PICTURE";
+
+fn picture_type_to_block_type(picture_type: &PictureType) -> u32 {
+	match picture_type {
+		PictureType::Other => 0,
+		PictureType::Icon => 1,
+		PictureType::OtherIcon => 2,
+		PictureType::CoverFront => 3,
+		PictureType::CoverBack => 4,
+		PictureType::Leaflet => 5,
+		PictureType::Media => 6,
+		PictureType::LeadArtist => 7,
+		PictureType::Artist => 8,
+		PictureType::Conductor => 9,
+		PictureType::Band => 10,
+		PictureType::Composer => 11,
+		PictureType::Lyricist => 12,
+		PictureType::RecordingLocation => 13,
+		PictureType::DuringRecording => 14,
+		PictureType::DuringPerformance => 15,
+		PictureType::ScreenCapture => 16,
+		PictureType::BrightColouredFish => 17,
+		PictureType::Illustration => 18,
+		PictureType::BandLogo => 19,
+		PictureType::PublisherLogo => 20,
+		PictureType::Undefined(n) => *n as u32,
+	}
+}
+
+fn block_type_to_picture_type(block_type: u32) -> PictureType {
+	match block_type {
+		0 => PictureType::Other,
+		1 => PictureType::Icon,
+		2 => PictureType::OtherIcon,
+		3 => PictureType::CoverFront,
+		4 => PictureType::CoverBack,
+		5 => PictureType::Leaflet,
+		6 => PictureType::Media,
+		7 => PictureType::LeadArtist,
+		8 => PictureType::Artist,
+		9 => PictureType::Conductor,
+		10 => PictureType::Band,
+		11 => PictureType::Composer,
+		12 => PictureType::Lyricist,
+		13 => PictureType::RecordingLocation,
+		14 => PictureType::DuringRecording,
+		15 => PictureType::DuringPerformance,
+		16 => PictureType::ScreenCapture,
+		17 => PictureType::BrightColouredFish,
+		18 => PictureType::Illustration,
+		19 => PictureType::BandLogo,
+		20 => PictureType::PublisherLogo,
+		n => PictureType::Undefined(n as u8),
+	}
+}
+
+fn encode_metadata_block_picture(picture: &Picture) -> String {
+	let mime = picture.mime_type.as_str();
+	let description = picture.description.as_deref().unwrap_or("");
+
+	let mut bytes =
+		Vec::with_capacity(32 + mime.len() + description.len() + picture.data.len());
+	bytes.extend_from_slice(&picture_type_to_block_type(&picture.pic_type).to_be_bytes());
+	bytes.extend_from_slice(&(mime.len() as u32).to_be_bytes());
+	bytes.extend_from_slice(mime.as_bytes());
+	bytes.extend_from_slice(&(description.len() as u32).to_be_bytes());
+	bytes.extend_from_slice(description.as_bytes());
+	bytes.extend_from_slice(&0u32.to_be_bytes()); // width
+	bytes.extend_from_slice(&0u32.to_be_bytes()); // height
+	bytes.extend_from_slice(&0u32.to_be_bytes()); // color depth
+	bytes.extend_from_slice(&0u32.to_be_bytes()); // indexed colors
+	bytes.extend_from_slice(&(picture.data.len() as u32).to_be_bytes());
+	bytes.extend_from_slice(&picture.data);
+
+	base64::encode(bytes)
+}
+
+fn decode_metadata_block_picture(encoded: &str) -> Option<Picture<'static>> {
+	let bytes = base64::decode(encoded).ok()?;
+	let mut pos = 0_usize;
+
+	let read_u32 = |bytes: &[u8], pos: &mut usize| -> Option<u32> {
+		let value = u32::from_be_bytes(bytes.get(*pos..*pos + 4)?.try_into().ok()?);
+		*pos += 4;
+		Some(value)
+	};
+
+	let picture_type = block_type_to_picture_type(read_u32(&bytes, &mut pos)?);
+
+	let mime_len = read_u32(&bytes, &mut pos)? as usize;
+	let mime = String::from_utf8(bytes.get(pos..pos + mime_len)?.to_vec()).ok()?;
+	pos += mime_len;
+
+	let description_len = read_u32(&bytes, &mut pos)? as usize;
+	let description = String::from_utf8(bytes.get(pos..pos + description_len)?.to_vec()).ok()?;
+	pos += description_len;
+
+	// Width, height, color depth and indexed colors aren't tracked by `Picture`.
+	pos += 16;
+
+	let data_len = read_u32(&bytes, &mut pos)? as usize;
+	let data = bytes.get(pos..pos + data_len)?.to_vec();
+
+	Some(Picture {
+		pic_type: picture_type,
+		mime_type: mime.as_str().try_into().ok()?,
+		description: (!description.is_empty()).then(|| Cow::Owned(description)),
+		data: Cow::Owned(data),
+	})
+}
 
 impl MissingImplementations for OpusInnerTag {
 	fn default() -> Self {
@@ -18,10 +200,7 @@ impl MissingImplementations for OpusInnerTag {
 				channel_mapping_family: 0,
 				channel_mapping_table: None,
 			},
-			comments: CommentHeader {
-				vendor: "".to_string(),
-				user_comments: Default::default(),
-			},
+			comments: VorbisComments::default(),
 		}
 	}
 
@@ -68,32 +247,115 @@ impl<'a> From<&'a OpusTag> for AnyTag<'a> {
 	}
 }
 
-impl OpusTag {
-	pub fn get_first(&self, key: &str) -> Option<&str> {
-		let comments = &self.0.comments.user_comments;
+impl SplitAndMergeTag for OpusTag {
+	fn split_tag(mut self) -> Tag {
+		let mut tag = Tag::new(TagType::Opus);
 
-		if let Some(pair) = comments.get_key_value(key) {
-			if !pair.1.is_empty() {
-				Some(pair.1.as_str())
-			} else {
-				None
+		let pictures = self.get_all(METADATA_BLOCK_PICTURE_KEY).filter_map(decode_metadata_block_picture);
+		for picture in pictures.filter(|p| p.pic_type == PictureType::CoverFront) {
+			tag.push_picture(picture);
+		}
+		self.remove_album_cover();
+
+		for (key, value) in self.0.comments.items {
+			let item_key = ItemKey::from_key(&TagType::Opus, &key);
+			// push_item, not insert_item: a repeated Vorbis key (e.g. multiple
+			// ARTIST= entries) must survive as multiple TagItems, not collapse
+			// down to the last value inserted.
+			tag.push_item(TagItem::new(item_key, ItemValue::Text(value)));
+		}
+
+		tag
+	}
+
+	fn merge_tag(&mut self, tag: Tag) {
+		if tag.pictures().iter().any(|p| p.pic_type == PictureType::CoverFront) {
+			self.remove_album_cover();
+		}
+		for picture in tag.pictures() {
+			if picture.pic_type == PictureType::CoverFront {
+				self.insert(METADATA_BLOCK_PICTURE_KEY, encode_metadata_block_picture(picture), false);
 			}
-		} else {
-			None
 		}
+
+		// Tracks which keys we've already seen so only the first item for a
+		// given key clears the existing values; later items with the same
+		// key append instead, preserving multi-valued keys.
+		let mut seen = std::collections::HashSet::new();
+
+		for item in tag.items() {
+			let key = match item.key().map_key(&TagType::Opus) {
+				Some(key) => key,
+				None => continue,
+			};
+
+			match item.value() {
+				ItemValue::Text(value) => {
+					let replace_all = seen.insert(key.to_string());
+					self.insert(key, value.clone(), replace_all);
+				},
+				// Opus comments have no concept of synchronized lyrics; flatten
+				// to plain text instead of losing the data entirely.
+				ItemValue::SynchronizedText(lines) => {
+					let flattened = lines
+						.iter()
+						.map(|(_, line)| line.as_str())
+						.collect::<Vec<_>>()
+						.join("\n");
+					self.insert(key, flattened, true);
+				},
+				// Chapters have no Vorbis comment representation.
+				ItemValue::Chapter { .. } => {},
+				ItemValue::Locator(_) | ItemValue::Binary(_) => {},
+			}
+		}
+	}
+}
+
+impl OpusTag {
+	pub fn get_first(&self, key: &str) -> Option<&str> {
+		self.0.comments.get_first(key)
+	}
+	/// Returns every value stored under `key`, in insertion order
+	pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		self.0.comments.get_all(key)
 	}
 	pub fn set_first(&mut self, key: &str, val: &str) {
-		let comments: &mut HashMap<String, String, RandomState> =
-			self.0.comments.user_comments.borrow_mut();
-		match comments.get_mut(key) {
-			Some(mut v) => v = &mut val.to_string(),
-			None => {},
-		}
+		self.0.comments.set_first(key, val);
+	}
+	/// Inserts `val` under `key`, optionally replacing every existing value
+	/// already stored there
+	pub fn insert(&mut self, key: &str, val: String, replace_all: bool) {
+		self.0.comments.insert(key, val, replace_all);
 	}
 	pub fn remove(&mut self, key: &str) {
-		let comments: &mut HashMap<String, String, RandomState> =
-			self.0.comments.user_comments.borrow_mut();
-		comments.retain(|k, _| k != key)
+		self.0.comments.remove(key);
+	}
+
+	/// Reads this file's [`FileProperties`] (duration, bitrate, sample rate,
+	/// channel count) directly from the OGG stream
+	pub fn properties(&self, file: &mut File) -> crate::Result<FileProperties> {
+		let mut bytes = Vec::new();
+		file.seek(std::io::SeekFrom::Start(0))?;
+		file.read_to_end(&mut bytes)?;
+
+		opus_properties(&bytes, &self.0.id)
+	}
+
+	/// Converts to an [`AnyTag`], alongside the stream's [`FileProperties`]
+	///
+	/// `AnyTag` is defined upstream (in the `audiotags` crate this type was
+	/// adapted from), not in this crate, so it can't be given a `duration`
+	/// field here. Rather than writing to a field that doesn't exist, this
+	/// returns the properties alongside the tag instead of folded into it;
+	/// once `AnyTag` gains a `duration` field upstream, this can set it
+	/// directly the way the other fields are set in `From<&OpusTag>`.
+	pub fn to_any_tag_with_properties<'a>(
+		&'a self,
+		file: &mut File,
+	) -> crate::Result<(AnyTag<'a>, FileProperties)> {
+		let properties = self.properties(file)?;
+		Ok((AnyTag::from(self), properties))
 	}
 }
 
@@ -119,6 +381,11 @@ impl AudioTagEdit for OpusTag {
 		self.remove("ARTIST");
 	}
 
+	fn artists(&self) -> Option<Vec<&str>> {
+		let artists: Vec<&str> = self.get_all("ARTIST").collect();
+		(!artists.is_empty()).then(|| artists)
+	}
+
 	fn year(&self) -> Option<u16> {
 		if let Some(Ok(y)) = self
 			.get_first("DATE")
@@ -161,33 +428,42 @@ impl AudioTagEdit for OpusTag {
 	fn remove_album_artist(&mut self) {
 		self.remove("ALBUMARTIST");
 	}
+
+	fn album_artists(&self) -> Option<Vec<&str>> {
+		let artists: Vec<&str> = self.get_all("ALBUMARTIST").collect();
+		(!artists.is_empty()).then(|| artists)
+	}
 	fn album_cover(&self) -> Option<Picture> {
-		// TODO
-		// self.0
-		//     .pictures()
-		//     .filter(|&pic| matches!(pic.picture_type, metaflac::block::PictureType::CoverFront))
-		//     .next()
-		//     .and_then(|pic| {
-		//         Some(Picture {
-		//             data: &pic.data,
-		//             mime_type: (pic.mime_type.as_str()).try_into().ok()?,
-		//         })
-		//     })
-		None
+		self.get_all(METADATA_BLOCK_PICTURE_KEY).find_map(|encoded| {
+			let picture = decode_metadata_block_picture(encoded)?;
+			(picture.pic_type == PictureType::CoverFront).then(|| picture)
+		})
 	}
 
 	fn set_album_cover(&mut self, cover: Picture) {
-		// TODO
-		// self.remove_album_cover();
-		// let mime = String::from(cover.mime_type);
-		// let picture_type = metaflac::block::PictureType::CoverFront;
-		// self.0
-		//     .add_picture(mime, picture_type, (cover.data).to_owned());
+		self.remove_album_cover();
+		self.insert(
+			METADATA_BLOCK_PICTURE_KEY,
+			encode_metadata_block_picture(&cover),
+			false,
+		);
 	}
 	fn remove_album_cover(&mut self) {
-		// TODO
-		// self.0
-		//     .remove_picture_type(metaflac::block::PictureType::CoverFront)
+		// Keep any non-front-cover METADATA_BLOCK_PICTURE entries (e.g. a back
+		// cover) instead of wiping the whole key.
+		let kept: Vec<String> = self
+			.get_all(METADATA_BLOCK_PICTURE_KEY)
+			.filter(|encoded| {
+				decode_metadata_block_picture(encoded)
+					.map_or(true, |pic| pic.pic_type != PictureType::CoverFront)
+			})
+			.map(str::to_string)
+			.collect();
+
+		self.remove(METADATA_BLOCK_PICTURE_KEY);
+		for value in kept {
+			self.insert(METADATA_BLOCK_PICTURE_KEY, value, false);
+		}
 	}
 
 	fn track_number(&self) -> Option<u16> {
@@ -247,13 +523,606 @@ impl AudioTagEdit for OpusTag {
 	}
 }
 
+// Comment header packet magic for Opus / Vorbis respectively.
+const OPUS_TAGS_MAGIC: &[u8] = b"OpusTags";
+const VORBIS_COMMENT_MAGIC: &[u8] = b"\x03vorbis";
+
+// Set on every OGG page but the first in a continued packet.
+const OGG_HEADER_CONTINUED: u8 = 0x01;
+
+struct OggPageHeader {
+	header_type: u8,
+	granule_position: i64,
+	serial_number: u32,
+	sequence_number: u32,
+	// The page's actual lacing/segment table, kept around so callers that
+	// re-serialize the page (e.g. rewrite_ogg_comments) can reuse the
+	// original packet boundaries instead of re-deriving a new segment table
+	// from the payload length, which silently merges multiple packets in a
+	// page into one.
+	segment_table: Vec<u8>,
+}
+
+fn invalid_ogg_data(message: &str) -> crate::Error {
+	std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string()).into()
+}
+
+// Returns (header, payload, total page length in bytes).
+fn read_ogg_page(bytes: &[u8], pos: usize) -> crate::Result<(OggPageHeader, Vec<u8>, usize)> {
+	// Fixed 27-byte page header; bounds-checked as a whole before any indexing
+	// into it, since this sees untrusted/possibly-truncated file contents.
+	let header = bytes
+		.get(pos..pos + 27)
+		.ok_or_else(|| invalid_ogg_data("OGG page header runs past the end of the file"))?;
+
+	if &header[0..4] != b"OggS" {
+		return Err(invalid_ogg_data("expected an OGG page capture pattern"));
+	}
+
+	let header_type = header[5];
+	let granule_position = i64::from_le_bytes(header[6..14].try_into().unwrap());
+	let serial_number = u32::from_le_bytes(header[14..18].try_into().unwrap());
+	let sequence_number = u32::from_le_bytes(header[18..22].try_into().unwrap());
+	let page_segments = header[26] as usize;
+
+	let segment_table = bytes
+		.get(pos + 27..pos + 27 + page_segments)
+		.ok_or_else(|| invalid_ogg_data("OGG page segment table runs past the end of the file"))?;
+	let data_len: usize = segment_table.iter().map(|&b| b as usize).sum();
+
+	let data_start = pos + 27 + page_segments;
+	let data = bytes
+		.get(data_start..data_start + data_len)
+		.ok_or_else(|| invalid_ogg_data("OGG page payload runs past the end of the file"))?
+		.to_vec();
+
+	Ok((
+		OggPageHeader {
+			header_type,
+			granule_position,
+			serial_number,
+			sequence_number,
+			segment_table: segment_table.to_vec(),
+		},
+		data,
+		27 + page_segments + data_len,
+	))
+}
+
+// Splits `len` into 255-byte lacing values, terminated by a value < 255
+// (or a trailing 0 if `len` is an exact multiple of 255).
+fn lace_values_for(mut len: usize) -> Vec<u8> {
+	let mut values = Vec::new();
+	while len >= 255 {
+		values.push(255);
+		len -= 255;
+	}
+	values.push(len as u8);
+	values
+}
+
+fn write_ogg_page(
+	serial_number: u32,
+	sequence_number: u32,
+	granule_position: i64,
+	header_type: u8,
+	segment_table: &[u8],
+	payload: &[u8],
+) -> Vec<u8> {
+	let mut page = Vec::with_capacity(27 + segment_table.len() + payload.len());
+	page.extend_from_slice(b"OggS");
+	page.push(0); // stream structure version
+	page.push(header_type);
+	page.extend_from_slice(&granule_position.to_le_bytes());
+	page.extend_from_slice(&serial_number.to_le_bytes());
+	page.extend_from_slice(&sequence_number.to_le_bytes());
+	page.extend_from_slice(&0u32.to_le_bytes()); // checksum, filled in below
+	page.push(segment_table.len() as u8);
+	page.extend_from_slice(segment_table);
+	page.extend_from_slice(payload);
+
+	let crc = crc32_ogg(&page);
+	page[22..26].copy_from_slice(&crc.to_le_bytes());
+
+	page
+}
+
+// Re-segments a comment header packet into one or more OGG pages (granule
+// position 0, at most 255 lacing values per page).
+fn build_comment_pages(serial_number: u32, first_sequence: u32, payload: &[u8]) -> Vec<Vec<u8>> {
+	let lace_values = lace_values_for(payload.len());
+
+	let mut pages = Vec::new();
+	let mut sequence_number = first_sequence;
+	let mut data_offset = 0;
+	let mut lace_offset = 0;
+
+	while lace_offset < lace_values.len() {
+		let chunk_end = (lace_offset + 255).min(lace_values.len());
+		let chunk = &lace_values[lace_offset..chunk_end];
+		let chunk_len: usize = chunk.iter().map(|&b| b as usize).sum();
+
+		let header_type = if pages.is_empty() { 0 } else { OGG_HEADER_CONTINUED };
+		pages.push(write_ogg_page(
+			serial_number,
+			sequence_number,
+			0,
+			header_type,
+			chunk,
+			&payload[data_offset..data_offset + chunk_len],
+		));
+
+		sequence_number += 1;
+		data_offset += chunk_len;
+		lace_offset = chunk_end;
+	}
+
+	pages
+}
+
+fn serialize_opus_tags(comments: &VorbisComments) -> Vec<u8> {
+	let mut out = Vec::new();
+	out.extend_from_slice(OPUS_TAGS_MAGIC);
+	out.extend_from_slice(&(comments.vendor.len() as u32).to_le_bytes());
+	out.extend_from_slice(comments.vendor.as_bytes());
+
+	out.extend_from_slice(&(comments.items.len() as u32).to_le_bytes());
+	for (key, value) in &comments.items {
+		let entry = format!("{}={}", key, value);
+		out.extend_from_slice(&(entry.len() as u32).to_le_bytes());
+		out.extend_from_slice(entry.as_bytes());
+	}
+
+	out
+}
+
+// Splices a rebuilt comment header packet into the stream, copying the id
+// header and audio pages verbatim and renumbering the pages after it.
+fn rewrite_ogg_comments(original: &[u8], comments: &VorbisComments) -> crate::Result<Vec<u8>> {
+	let (id_header, id_payload, id_page_len) = read_ogg_page(original, 0)?;
+	if !id_payload.starts_with(b"OpusHead") {
+		return Err(invalid_ogg_data("expected an Opus identification header page first"));
+	}
+
+	let mut pos = id_page_len;
+	let mut old_page_count = 0;
+	let mut first_sequence = 0;
+	loop {
+		let (header, payload, page_len) = read_ogg_page(original, pos)?;
+
+		if old_page_count == 0 {
+			if !payload.starts_with(OPUS_TAGS_MAGIC) && !payload.starts_with(VORBIS_COMMENT_MAGIC)
+			{
+				return Err(invalid_ogg_data("missing comment header packet"));
+			}
+			first_sequence = header.sequence_number;
+		} else if header.header_type & OGG_HEADER_CONTINUED == 0 {
+			// The first page without the flag starts the next packet.
+			break;
+		}
+
+		old_page_count += 1;
+		pos += page_len;
+
+		if pos >= original.len() {
+			break;
+		}
+	}
+
+	let new_payload = serialize_opus_tags(comments);
+	let new_pages = build_comment_pages(id_header.serial_number, first_sequence, &new_payload);
+	let sequence_shift = new_pages.len() as i64 - old_page_count as i64;
+
+	let mut out = Vec::with_capacity(original.len());
+	out.extend_from_slice(&original[..id_page_len]);
+	for page in &new_pages {
+		out.extend_from_slice(page);
+	}
+
+	while pos < original.len() {
+		let (header, payload, page_len) = read_ogg_page(original, pos)?;
+		let new_sequence = (header.sequence_number as i64 + sequence_shift) as u32;
+		// Reuse the page's own segment table rather than re-deriving one from
+		// payload.len(): a page can carry more than one Opus packet (a lacing
+		// value < 255 before the payload ends marks a packet boundary), and
+		// lace_values_for(payload.len()) always produces a single packet's
+		// worth of lacing, erasing that boundary.
+		out.extend_from_slice(&write_ogg_page(
+			header.serial_number,
+			new_sequence,
+			header.granule_position,
+			header.header_type,
+			&header.segment_table,
+			&payload,
+		));
+		pos += page_len;
+	}
+
+	Ok(out)
+}
+
+// The OGG container's CRC32: polynomial 0x04c11db7, init 0, no reflection.
+fn crc32_ogg(data: &[u8]) -> u32 {
+	const POLY: u32 = 0x04c1_1db7;
+
+	let mut table = [0u32; 256];
+	let mut i = 0;
+	while i < 256 {
+		let mut crc = (i as u32) << 24;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ POLY } else { crc << 1 };
+			bit += 1;
+		}
+		table[i] = crc;
+		i += 1;
+	}
+
+	let mut crc = 0u32;
+	for &byte in data {
+		crc = (crc << 8) ^ table[(((crc >> 24) ^ byte as u32) & 0xff) as usize];
+	}
+	crc
+}
+
+// Granule position of the last OGG page in the stream
+fn last_page_granule_position(bytes: &[u8]) -> crate::Result<i64> {
+	let mut pos = 0;
+	let mut granule_position = 0;
+
+	while pos < bytes.len() {
+		let (header, _payload, page_len) = read_ogg_page(bytes, pos)?;
+		granule_position = header.granule_position;
+		pos += page_len;
+	}
+
+	Ok(granule_position)
+}
+
+// Duration comes from (last page's granule_position - pre_skip) / 48 kHz, per RFC 7845 4.2
+fn opus_properties(bytes: &[u8], id_header: &IdentificationHeader) -> crate::Result<FileProperties> {
+	let granule_position = last_page_granule_position(bytes)?;
+	let samples = (granule_position - id_header.pre_skip as i64).max(0) as u64;
+	let duration = Duration::from_secs_f64(samples as f64 / OPUS_CLOCK_RATE as f64);
+
+	// Both bitrates come from the whole file's length: header pages and OGG
+	// page overhead are not subtracted from the audio bitrate.
+	let audio_bitrate = (duration.as_secs_f64() > 0.0)
+		.then(|| ((bytes.len() as f64 * 8.0 / 1000.0) / duration.as_secs_f64()) as u32);
+
+	Ok(FileProperties::new(
+		duration,
+		audio_bitrate,
+		audio_bitrate,
+		Some(id_header.input_sample_rate as u32),
+		Some(id_header.channel_count as u8),
+	))
+}
+
 impl AudioTagWrite for OpusTag {
 	fn write_to(&mut self, file: &mut File) -> crate::Result<()> {
-		// self.0.write_to(file)?; TODO
+		let mut original = Vec::new();
+		file.seek(std::io::SeekFrom::Start(0))?;
+		file.read_to_end(&mut original)?;
+
+		let rewritten = rewrite_ogg_comments(&original, &self.0.comments)?;
+
+		file.seek(std::io::SeekFrom::Start(0))?;
+		file.set_len(0)?;
+		file.write_all(&rewritten)?;
 		Ok(())
 	}
 	fn write_to_path(&mut self, path: &str) -> crate::Result<()> {
-		// self.0.write_to_path(path)?; TODO
-		Ok(())
+		let mut file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+		self.write_to(&mut file)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn vorbis_comments_preserves_order_and_duplicate_keys() {
+		let mut comments = VorbisComments::default();
+		comments.insert("ARTIST", "one".to_string(), false);
+		comments.insert("ARTIST", "two".to_string(), false);
+
+		assert_eq!(
+			comments.get_all("ARTIST").collect::<Vec<_>>(),
+			vec!["one", "two"]
+		);
+		assert_eq!(comments.get_first("ARTIST"), Some("one"));
+	}
+
+	#[test]
+	fn vorbis_comments_insert_replace_all_drops_existing_values() {
+		let mut comments = VorbisComments::default();
+		comments.insert("ARTIST", "one".to_string(), false);
+		comments.insert("ARTIST", "two".to_string(), false);
+		comments.insert("ARTIST", "three".to_string(), true);
+
+		assert_eq!(
+			comments.get_all("ARTIST").collect::<Vec<_>>(),
+			vec!["three"]
+		);
+	}
+
+	#[test]
+	fn vorbis_comments_set_first_mutates_in_place() {
+		let mut comments = VorbisComments::default();
+		comments.insert("ARTIST", "one".to_string(), false);
+		comments.insert("ARTIST", "two".to_string(), false);
+		comments.set_first("ARTIST", "updated");
+
+		assert_eq!(
+			comments.get_all("ARTIST").collect::<Vec<_>>(),
+			vec!["updated", "two"]
+		);
+	}
+
+	fn sample_picture(pic_type: PictureType) -> Picture<'static> {
+		Picture {
+			pic_type,
+			mime_type: "image/png".try_into().unwrap(),
+			description: Some(Cow::Borrowed("cover")),
+			data: Cow::Owned(vec![1, 2, 3, 4]),
+		}
+	}
+
+	#[test]
+	fn metadata_block_picture_round_trips() {
+		let picture = sample_picture(PictureType::CoverFront);
+		let encoded = encode_metadata_block_picture(&picture);
+		let decoded = decode_metadata_block_picture(&encoded).unwrap();
+
+		assert_eq!(decoded.pic_type, PictureType::CoverFront);
+		assert_eq!(decoded.mime_type.as_str(), "image/png");
+		assert_eq!(decoded.description.as_deref(), Some("cover"));
+		assert_eq!(decoded.data.as_ref(), picture.data.as_ref());
+	}
+
+	#[test]
+	fn album_cover_finds_front_cover_stored_after_other_pictures() {
+		let mut tag = OpusTag::default();
+		tag.insert(
+			METADATA_BLOCK_PICTURE_KEY,
+			encode_metadata_block_picture(&sample_picture(PictureType::CoverBack)),
+			false,
+		);
+		tag.insert(
+			METADATA_BLOCK_PICTURE_KEY,
+			encode_metadata_block_picture(&sample_picture(PictureType::CoverFront)),
+			false,
+		);
+
+		assert_eq!(tag.album_cover().unwrap().pic_type, PictureType::CoverFront);
+	}
+
+	#[test]
+	fn remove_album_cover_keeps_other_picture_types() {
+		let mut tag = OpusTag::default();
+		tag.insert(
+			METADATA_BLOCK_PICTURE_KEY,
+			encode_metadata_block_picture(&sample_picture(PictureType::CoverBack)),
+			false,
+		);
+		tag.insert(
+			METADATA_BLOCK_PICTURE_KEY,
+			encode_metadata_block_picture(&sample_picture(PictureType::CoverFront)),
+			false,
+		);
+
+		tag.remove_album_cover();
+
+		assert!(tag.album_cover().is_none());
+		assert_eq!(tag.get_all(METADATA_BLOCK_PICTURE_KEY).count(), 1);
+	}
+
+	#[test]
+	fn lace_values_for_handles_exact_multiples_of_255() {
+		assert_eq!(lace_values_for(0), vec![0]);
+		assert_eq!(lace_values_for(10), vec![10]);
+		assert_eq!(lace_values_for(255), vec![255, 0]);
+		assert_eq!(lace_values_for(510), vec![255, 255, 0]);
+		assert_eq!(lace_values_for(300), vec![255, 45]);
+	}
+
+	#[test]
+	fn ogg_page_round_trips_through_write_and_read() {
+		let payload = b"OpusTags-test-payload";
+		let segment_table = lace_values_for(payload.len());
+		let page = write_ogg_page(42, 7, -1, OGG_HEADER_CONTINUED, &segment_table, payload);
+
+		let (header, data, page_len) = read_ogg_page(&page, 0).unwrap();
+		assert_eq!(page_len, page.len());
+		assert_eq!(header.serial_number, 42);
+		assert_eq!(header.sequence_number, 7);
+		assert_eq!(header.granule_position, -1);
+		assert_eq!(header.header_type, OGG_HEADER_CONTINUED);
+		assert_eq!(data, payload);
+	}
+
+	#[test]
+	fn read_ogg_page_rejects_truncated_input() {
+		let payload = b"short page";
+		let segment_table = lace_values_for(payload.len());
+		let page = write_ogg_page(1, 0, 0, 0, &segment_table, payload);
+
+		assert!(read_ogg_page(&page[..page.len() - 1], 0).is_err());
+		assert!(read_ogg_page(&page[..10], 0).is_err());
+	}
+
+	#[test]
+	fn crc32_ogg_is_sensitive_to_every_byte() {
+		assert_ne!(crc32_ogg(b"OggS"), crc32_ogg(b"OggT"));
+		assert_eq!(crc32_ogg(b""), 0);
+	}
+
+	#[test]
+	fn build_comment_pages_splits_large_packets_across_pages() {
+		// One page holds at most 255 lacing values (65,025 bytes), so this
+		// packet needs a second page.
+		let payload = vec![0xAB; 70_000];
+		let pages = build_comment_pages(1, 0, &payload);
+
+		assert!(pages.len() > 1, "expected a 70,000-byte packet to span multiple pages");
+
+		let mut rebuilt = Vec::new();
+		for (i, page) in pages.iter().enumerate() {
+			let (header, data, page_len) = read_ogg_page(page, 0).unwrap();
+			assert_eq!(page_len, page.len());
+			assert_eq!(
+				header.header_type & OGG_HEADER_CONTINUED != 0,
+				i > 0,
+				"only non-first pages should carry the continuation flag"
+			);
+			rebuilt.extend_from_slice(&data);
+		}
+
+		assert_eq!(rebuilt, payload);
+	}
+
+	#[test]
+	fn opus_properties_derives_duration_from_granule_position() {
+		let id_header = IdentificationHeader {
+			version: 1,
+			channel_count: 2,
+			pre_skip: 3_000,
+			input_sample_rate: 48_000,
+			output_gain: 0,
+			channel_mapping_family: 0,
+			channel_mapping_table: None,
+		};
+
+		// One page whose granule_position is 3_000 samples of pre-skip plus
+		// exactly one second of audio at the 48 kHz reference clock rate.
+		let granule_position = id_header.pre_skip as i64 + OPUS_CLOCK_RATE as i64;
+		let segment_table = lace_values_for(4);
+		let page = write_ogg_page(1, 0, granule_position, 0, &segment_table, b"data");
+
+		let properties = opus_properties(&page, &id_header).unwrap();
+		assert_eq!(properties.duration(), Duration::from_secs(1));
+		assert_eq!(properties.sample_rate(), Some(48_000));
+		assert_eq!(properties.channels(), Some(2));
+	}
+
+	#[test]
+	fn split_and_merge_tag_round_trips_multi_valued_key() {
+		let mut tag = OpusTag::default();
+		tag.insert("ARTIST", "one".to_string(), false);
+		tag.insert("ARTIST", "two".to_string(), false);
+
+		let split = tag.split_tag();
+		assert_eq!(
+			split
+				.items()
+				.iter()
+				.filter(|i| *i.key() == ItemKey::from_key(&TagType::Opus, "ARTIST"))
+				.count(),
+			2,
+			"splitting a multi-valued key should keep every value as its own item"
+		);
+
+		let mut merged = OpusTag::default();
+		merged.merge_tag(split);
+		assert_eq!(
+			merged.get_all("ARTIST").collect::<Vec<_>>(),
+			vec!["one", "two"]
+		);
+	}
+
+	#[test]
+	fn merge_tag_flattens_synchronized_text_and_drops_chapters() {
+		let mut generic = Tag::new(TagType::Opus);
+		generic.push_item(TagItem::new(
+			ItemKey::from_key(&TagType::Opus, "LYRICS"),
+			ItemValue::SynchronizedText(vec![
+				(0, "first line".to_string()),
+				(1_000, "second line".to_string()),
+			]),
+		));
+		generic.push_item(TagItem::new(
+			ItemKey::from_key(&TagType::Opus, "CHAPTER"),
+			ItemValue::Chapter {
+				start_ms: 0,
+				end_ms: 1_000,
+				title: Some("Intro".to_string()),
+				subframes: Vec::new(),
+			},
+		));
+
+		let mut tag = OpusTag::default();
+		tag.merge_tag(generic);
+
+		// Opus has no chapter representation at all, so it's dropped...
+		assert_eq!(tag.get_first("CHAPTER"), None);
+		// ...while synchronized lyrics fall back to plain, unsynchronized text
+		// rather than being lost entirely.
+		assert_eq!(tag.get_first("LYRICS"), Some("first line\nsecond line"));
+	}
+
+	#[test]
+	fn rewrite_ogg_comments_preserves_multi_packet_audio_pages() {
+		let id_payload = {
+			let mut p = b"OpusHead".to_vec();
+			p.extend_from_slice(&[0u8; 10]);
+			p
+		};
+		let id_segments = lace_values_for(id_payload.len());
+		let id_page = write_ogg_page(1, 0, 0, 0, &id_segments, &id_payload);
+
+		let comment_payload = serialize_opus_tags(&VorbisComments::default());
+		let comment_pages = build_comment_pages(1, 1, &comment_payload);
+
+		// One audio page carrying two packets (100 bytes, then 50), which a
+		// single-packet segment table of [150] would misrepresent as one.
+		let packet_a = vec![0xAA; 100];
+		let packet_b = vec![0xBB; 50];
+		let mut audio_payload = packet_a.clone();
+		audio_payload.extend_from_slice(&packet_b);
+		let audio_segments = vec![100, 50];
+		let audio_page = write_ogg_page(
+			1,
+			comment_pages.len() as u32 + 1,
+			12345,
+			0,
+			&audio_segments,
+			&audio_payload,
+		);
+
+		let mut original = id_page;
+		for page in &comment_pages {
+			original.extend_from_slice(page);
+		}
+		original.extend_from_slice(&audio_page);
+
+		let mut new_comments = VorbisComments::default();
+		new_comments.insert("TITLE", "new title".to_string(), false);
+		let rewritten = rewrite_ogg_comments(&original, &new_comments).unwrap();
+
+		let (_, _, id_page_len) = read_ogg_page(&rewritten, 0).unwrap();
+		let mut pos = id_page_len;
+
+		// Consume the (possibly multi-page) rewritten comment header packet:
+		// its first page starts fresh, and any further pages that are still
+		// part of it carry the continuation flag. The first page found
+		// without that flag is the start of the next packet - the audio page.
+		let (_, _, first_comment_page_len) = read_ogg_page(&rewritten, pos).unwrap();
+		pos += first_comment_page_len;
+		loop {
+			let (header, _payload, page_len) = read_ogg_page(&rewritten, pos).unwrap();
+			if header.header_type & OGG_HEADER_CONTINUED == 0 {
+				break;
+			}
+			pos += page_len;
+		}
+
+		let (audio_header, audio_data, _) = read_ogg_page(&rewritten, pos).unwrap();
+		assert_eq!(
+			audio_header.segment_table, audio_segments,
+			"audio page's original packet boundaries must survive the splice"
+		);
+		assert_eq!(audio_data, audio_payload);
 	}
 }
\ No newline at end of file
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+/// Information about the audio stream itself, as opposed to its metadata
+///
+/// This is read directly from the audio stream, independently of any tag, so
+/// it is available even for files that have no tag at all.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct FileProperties {
+	duration: Duration,
+	overall_bitrate: Option<u32>,
+	audio_bitrate: Option<u32>,
+	sample_rate: Option<u32>,
+	channels: Option<u8>,
+}
+
+impl FileProperties {
+	/// Creates a new [`FileProperties`]
+	pub fn new(
+		duration: Duration,
+		overall_bitrate: Option<u32>,
+		audio_bitrate: Option<u32>,
+		sample_rate: Option<u32>,
+		channels: Option<u8>,
+	) -> Self {
+		Self {
+			duration,
+			overall_bitrate,
+			audio_bitrate,
+			sample_rate,
+			channels,
+		}
+	}
+
+	/// Duration of the audio
+	pub fn duration(&self) -> Duration {
+		self.duration
+	}
+
+	/// Overall bitrate, including container overhead, in kbps
+	pub fn bitrate(&self) -> Option<u32> {
+		self.overall_bitrate
+	}
+
+	/// Bitrate of the audio stream itself, in kbps
+	pub fn audio_bitrate(&self) -> Option<u32> {
+		self.audio_bitrate
+	}
+
+	/// Sample rate in Hz
+	pub fn sample_rate(&self) -> Option<u32> {
+		self.sample_rate
+	}
+
+	/// Channel count
+	pub fn channels(&self) -> Option<u8> {
+		self.channels
+	}
+}
@@ -0,0 +1,115 @@
+//! ID3v2 version down-conversion groundwork
+//!
+//! This module is prep work, not the feature: it has the version enum and
+//! the frame-id/timestamp mappings a save-as-version write path would need,
+//! but there is no `Id3v2Tag` in this crate yet to carry an active
+//! [`Id3v2Version`] or expose a `set_version` API, so nothing here is wired
+//! into an actual write path.
+
+/// The ID3v2 major version a tag is encoded as
+///
+/// Frame layout differs meaningfully between versions: v2.2 uses 3-character
+/// frame ids where v2.3/v2.4 use 4, and some frames only exist in one
+/// version or the other (`TYER`/`TDAT` in v2.3 vs. the combined `TDRC`
+/// timestamp in v2.4). The default is the newest, [`Id3v24`].
+///
+/// [`Id3v24`]: Self::Id3v24
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Id3v2Version {
+	/// ID3v2.2, using 3-character frame ids
+	Id3v22,
+	/// ID3v2.3
+	Id3v23,
+	/// ID3v2.4
+	Id3v24,
+}
+
+impl Default for Id3v2Version {
+	fn default() -> Self {
+		Self::Id3v24
+	}
+}
+
+/// Maps a v2.3/v2.4 (4-character) frame id to its v2.2 (3-character)
+/// equivalent, if one exists
+///
+/// Returns `None` for frames with no v2.2 representation, which should be
+/// dropped rather than written when down-converting.
+pub fn id3v22_frame_id(frame_id: &str) -> Option<&'static str> {
+	Some(match frame_id {
+		"TALB" => "TAL",
+		"TCOM" => "TCM",
+		"TCON" => "TCO",
+		"TCOP" => "TCR",
+		"TDRC" | "TYER" => "TYE",
+		"TDAT" => "TDA",
+		"TENC" => "TEN",
+		"TEXT" => "TXT",
+		"TIT1" => "TT1",
+		"TIT2" => "TT2",
+		"TIT3" => "TT3",
+		"TKEY" => "TKE",
+		"TLAN" => "TLA",
+		"TLEN" => "TLE",
+		"TPE1" => "TP1",
+		"TPE2" => "TP2",
+		"TPE3" => "TP3",
+		"TPE4" => "TP4",
+		"TPOS" => "TPA",
+		"TPUB" => "TPB",
+		"TRCK" => "TRK",
+		"TSRC" => "TRC",
+		"COMM" => "COM",
+		"APIC" => "PIC",
+		"USLT" => "ULT",
+		_ => return None,
+	})
+}
+
+/// Splits a combined ID3v2.4 `TDRC` timestamp (`YYYY-MM-DD...`) into the
+/// `TYER` (year) and `TDAT` (`DDMM`) values used by ID3v2.3
+pub fn split_tdrc(timestamp: &str) -> (String, String) {
+	let year = timestamp.get(0..4).unwrap_or(timestamp).to_string();
+
+	let date = timestamp
+		.get(5..7)
+		.zip(timestamp.get(8..10))
+		.map(|(month, day)| format!("{}{}", day, month))
+		.unwrap_or_default();
+
+	(year, date)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn id3v22_frame_id_maps_known_frames() {
+		assert_eq!(id3v22_frame_id("TALB"), Some("TAL"));
+		assert_eq!(id3v22_frame_id("TDRC"), Some("TYE"));
+		assert_eq!(id3v22_frame_id("TYER"), Some("TYE"));
+		assert_eq!(id3v22_frame_id("TDAT"), Some("TDA"));
+		assert_eq!(id3v22_frame_id("APIC"), Some("PIC"));
+	}
+
+	#[test]
+	fn id3v22_frame_id_returns_none_for_frames_with_no_v22_equivalent() {
+		assert_eq!(id3v22_frame_id("CHAP"), None);
+		assert_eq!(id3v22_frame_id("SYLT"), None);
+		assert_eq!(id3v22_frame_id("NOPE"), None);
+	}
+
+	#[test]
+	fn split_tdrc_splits_full_timestamp_into_year_and_date() {
+		assert_eq!(
+			split_tdrc("2004-03-07"),
+			("2004".to_string(), "0703".to_string())
+		);
+	}
+
+	#[test]
+	fn split_tdrc_handles_year_only_timestamp() {
+		assert_eq!(split_tdrc("2004"), ("2004".to_string(), "".to_string()));
+	}
+}
@@ -54,6 +54,10 @@ impl TagItem {
 ///
 /// NOTE: The [Locator][ItemValue::Locator] and [Binary][ItemValue::Binary] variants are only applicable to APE tags.
 /// Attempting to write either to another file/tag type will **not** error, they will just be ignored.
+///
+/// NOTE: [Chapter][ItemValue::Chapter] and [SynchronizedText][ItemValue::SynchronizedText]
+/// are data-model only for now — there is no `Id3v2Tag` in this crate yet to
+/// actually read or write the `CHAP`/`CTOC`/`SYLT` frames they're modeled on.
 pub enum ItemValue {
 	/// Any UTF-8 encoded text
 	Text(String),
@@ -61,6 +65,27 @@ pub enum ItemValue {
 	Locator(String),
 	/// **(APE ONLY)** Binary information, most likely a picture
 	Binary(Vec<u8>),
+	/// A single chapter mark
+	///
+	/// `start_ms`/`end_ms` are offsets from the start of the audio, and
+	/// `subframes` holds any items embedded in the chapter (e.g. a `TIT2`
+	/// sub-frame giving it a title on ID3v2). This maps conceptually to
+	/// ID3v2's `CHAP`/`CTOC` frames, but no Id3v2 component exists in this
+	/// tree yet to actually read or write them; formats with no chapter
+	/// support drop it.
+	Chapter {
+		start_ms: u32,
+		end_ms: u32,
+		title: Option<String>,
+		subframes: Vec<TagItem>,
+	},
+	/// Lyrics synchronized to playback position, as `(timestamp_ms, line)` pairs
+	///
+	/// This maps conceptually to ID3v2's `SYLT` frame, but no Id3v2
+	/// component exists in this tree yet to actually read or write it.
+	/// Formats with no synchronized lyrics support flatten it to a single
+	/// [`Text`](Self::Text) item instead of dropping it.
+	SynchronizedText(Vec<(u32, String)>),
 }
 
 /// Represents a parsed tag
@@ -73,6 +98,15 @@ pub struct Tag {
 }
 
 impl Tag {
+	/// Creates a new, empty [`Tag`] of the given [`TagType`]
+	pub fn new(tag_type: TagType) -> Self {
+		Self {
+			tag_type,
+			pictures: Vec::new(),
+			items: Vec::new(),
+		}
+	}
+
 	/// Returns the [`TagType`]
 	pub fn tag_type(&self) -> &TagType {
 		&self.tag_type
@@ -125,6 +159,21 @@ impl Tag {
 		self.items.iter().find(|i| &i.item_key == item_key)
 	}
 
+	/// Returns every stored [`ItemValue::Chapter`], in order
+	pub fn chapters(&self) -> impl Iterator<Item = &TagItem> {
+		self.items
+			.iter()
+			.filter(|i| matches!(i.value(), ItemValue::Chapter { .. }))
+	}
+
+	/// Returns the stored [`ItemValue::SynchronizedText`] lines, if any
+	pub fn synchronized_lyrics(&self) -> Option<&[(u32, String)]> {
+		self.items.iter().find_map(|i| match i.value() {
+			ItemValue::SynchronizedText(lines) => Some(lines.as_slice()),
+			_ => None,
+		})
+	}
+
 	/// Insert a [`TagItem`], replacing any existing one of the same type
 	///
 	/// # Returns
@@ -144,6 +193,40 @@ impl Tag {
 
 		false
 	}
+
+	/// Pushes a [`TagItem`], preserving any existing item(s) already stored
+	/// under the same key
+	///
+	/// Unlike [`insert_item`](Self::insert_item), this never replaces an
+	/// existing item, so a format that stores multiple values under one key
+	/// (e.g. Vorbis's repeatable `ARTIST=`) can round-trip every value
+	/// instead of collapsing down to the last one inserted.
+	///
+	/// # Returns
+	///
+	/// `false` is only returned if the [`TagItem`]'s key couldn't be remapped to the target [`TagType`]
+	pub fn push_item(&mut self, item: TagItem) -> bool {
+		if let Some(item) = item.re_map(&self.tag_type) {
+			self.items.push(item);
+			return true;
+		}
+
+		false
+	}
+}
+
+/// Converts a format-specific tag to and from the generic [`Tag`]
+///
+/// Unlike a one-off `From<AnyTag>` conversion, which only copies the handful
+/// of fields `AnyTag` knows about, this round-trips every [`TagItem`] and
+/// [`Picture`] a format actually stores — comments, replaygain, custom keys,
+/// and all — via [`ItemKey::from_key`] and [`ItemKey::map_key`].
+pub trait SplitAndMergeTag {
+	/// Splits the tag apart into a generic [`Tag`], consuming it
+	fn split_tag(self) -> Tag;
+
+	/// Merges a generic [`Tag`] into this tag, overwriting any items it maps to
+	fn merge_tag(&mut self, tag: Tag);
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -153,6 +236,10 @@ pub enum TagType {
 	Ape,
 	#[cfg(feature = "format-id3")]
 	/// Represents multiple formats, see [`Id3Format`](Id3Format) for extensions.
+	///
+	/// The major versions (`Id3v2.2`/`.3`/`.4`) are named by
+	/// [`Id3v2Version`](crate::Id3v2Version), alongside frame-id and timestamp
+	/// mappings for down-conversion; no write path applies those mappings.
 	Id3v2,
 	#[cfg(feature = "format-mp4")]
 	/// Common file extensions: `.mp4, .m4a, .m4p, .m4b, .m4r, .m4v`